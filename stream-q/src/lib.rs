@@ -1,24 +1,183 @@
 use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+
 use aws_config::BehaviorVersion;
 use aws_sdk_qbusiness::error::SdkError;
 use aws_sdk_qbusiness::operation::chat::{ChatError, ChatOutput};
 use aws_sdk_qbusiness::primitives::event_stream::EventReceiver;
-use aws_sdk_qbusiness::types::{ChatInputStream, ChatOutputStream, EndOfInputEvent, TextInputEvent};
+use aws_sdk_qbusiness::primitives::Blob;
+use aws_sdk_qbusiness::types::{
+    AttachmentInput, AttachmentInputEvent, ChatInputStream, ChatMode, ChatOutputStream,
+    EndOfInputEvent, TextInputEvent,
+};
 use aws_sdk_qbusiness::types::error::{ChatInputStreamError, ChatOutputStreamError};
-use pyo3::exceptions::PyStopAsyncIteration;
+use axum::extract::State;
+use axum::response::sse::{Event as SseEvent, Sse};
+use axum::routing::post;
+use axum::{Json, Router};
+use futures_util::StreamExt;
+use once_cell::sync::OnceCell;
 use pyo3::exceptions::PyException;
+use pyo3::exceptions::PyStopAsyncIteration;
 use pyo3::prelude::*;
-use tokio::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, oneshot, Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::Registry;
+use uuid::Uuid;
+
+// The owned `Runtime` lives behind a mutex so `Driver::stop()` can `.take()` it out and shut
+// it down by value; every other caller only ever needs a `Handle`, which stays valid to clone
+// and use for `spawn`/`block_on` right up until that shutdown actually happens.
+static RUNTIME: OnceCell<std::sync::Mutex<Option<Runtime>>> = OnceCell::new();
+static RUNTIME_HANDLE: OnceCell<tokio::runtime::Handle> = OnceCell::new();
+static LOGGER_CB: OnceCell<Py<PyAny>> = OnceCell::new();
+
+fn runtime() -> PyResult<tokio::runtime::Handle> {
+    RUNTIME_HANDLE.get().cloned().ok_or_else(|| PyException::new_err("stream_q.init() must be called before using the client"))
+}
+
+/// Starts the tokio runtime that backs every `Promise` returned from this module and routes
+/// Rust `tracing` events to `logger_cb`. Must be called exactly once before any other
+/// function; the returned `Driver` owns the runtime and can shut it down via `stop()`.
+#[pyfunction]
+fn init(logger_cb: Py<PyAny>, debug: bool) -> PyResult<Driver> {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| PyException::new_err(format!("failed to start tokio runtime: {e}")))?;
+
+    RUNTIME_HANDLE.set(rt.handle().clone()).map_err(|_| PyException::new_err("stream_q.init() has already been called"))?;
+    RUNTIME.set(std::sync::Mutex::new(Some(rt))).map_err(|_| PyException::new_err("stream_q.init() has already been called"))?;
+    LOGGER_CB.set(logger_cb).map_err(|_| PyException::new_err("stream_q.init() has already been called"))?;
+
+    let filter = if debug { LevelFilter::TRACE } else { LevelFilter::INFO };
+    let subscriber = Registry::default().with(filter).with(PyLoggerLayer);
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| PyException::new_err(format!("failed to install tracing subscriber: {e}")))?;
+
+    Ok(Driver { stopped: AtomicBool::new(false) })
+}
+
+/// A `tracing_subscriber::Layer` that formats every event as a single line and hands it to
+/// the Python callable stored in `LOGGER_CB`, acquiring the GIL per event.
+struct PyLoggerLayer;
+
+impl<S> tracing_subscriber::Layer<S> for PyLoggerLayer
+where
+    S: tracing::Subscriber,
+{
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        let Some(logger_cb) = LOGGER_CB.get() else { return };
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let line = format!(
+            "[{}] {}: {}{}",
+            metadata.level(),
+            metadata.target(),
+            visitor.message.unwrap_or_default(),
+            visitor.fields
+        );
+
+        Python::with_gil(|py| {
+            if let Err(e) = logger_cb.call1(py, (line,)) {
+                e.print(py);
+            }
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: Option<String>,
+    fields: String,
+}
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.fields.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Owns the tokio runtime started by `init`. Every other part of this module only ever holds
+/// a cloned `Handle`, so `stop()` can take the owned `Runtime` out of its mutex and actually
+/// shut it down, unlike a plain `&'static Runtime` which can't be moved out of a shared
+/// reference.
+#[pyclass]
+struct Driver {
+    stopped: AtomicBool,
+}
+
+#[pymethods]
+impl Driver {
+    /// Shuts the shared runtime down for real: in-flight `Promise`s are cancelled (their
+    /// `wait()` will raise once cancellation lands) and worker threads are given up to 5
+    /// seconds to drain before being dropped. Safe to call more than once; later calls are
+    /// no-ops since the runtime has already been taken.
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(rt) = RUNTIME.get().and_then(|m| m.lock().unwrap().take()) {
+            rt.shutdown_timeout(std::time::Duration::from_secs(5));
+        }
+    }
+}
+
+/// Wraps a background task spawned on the shared runtime. Callers can either block the
+/// current thread until it resolves with `wait()`, or poll `is_done()` and wrap the
+/// `Promise` in their own Python future.
+#[pyclass]
+struct Promise {
+    handle: Option<JoinHandle<PyResult<PyObject>>>,
+}
+
+impl Promise {
+    fn spawn<F>(fut: F) -> PyResult<Self>
+    where
+        F: Future<Output = PyResult<PyObject>> + Send + 'static,
+    {
+        Ok(Self { handle: Some(runtime()?.spawn(fut)) })
+    }
+}
+
+#[pymethods]
+impl Promise {
+    fn wait(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        let handle = self.handle.take()
+            .ok_or_else(|| PyException::new_err("Promise has already been resolved"))?;
+        let rt = runtime()?;
+
+        py.allow_threads(|| rt.block_on(handle))
+            .map_err(|e| PyException::new_err(format!("background task failed: {e}")))?
+    }
 
+    fn is_done(&self) -> bool {
+        match &self.handle {
+            Some(handle) => handle.is_finished(),
+            None => true,
+        }
+    }
+}
 
 #[pyfunction]
-fn new_q_client<'p>(py: Python<'p>, application_id: String) -> PyResult<&'p PyAny> {
-    pyo3_asyncio::tokio::future_into_py(py, async move {
+fn new_q_client(application_id: String) -> PyResult<Promise> {
+    Promise::spawn(async move {
         let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
         let client = aws_sdk_qbusiness::Client::new(&config);
-        let client = QBusiness {client, app_id: application_id};
-        Ok(client)
+        let client = QBusiness { client, app_id: application_id };
+        Python::with_gil(|py| Py::new(py, client).map(|obj| obj.into_py(py)))
     })
 }
 
@@ -30,10 +189,10 @@ struct QBusiness {
 
 #[pymethods]
 impl QBusiness {
-    fn list_applications<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+    fn list_applications(&self) -> PyResult<Promise> {
         let list_app_req = self.client.list_applications().send();
 
-        pyo3_asyncio::tokio::future_into_py(py, async move {
+        Promise::spawn(async move {
             let list_app = match list_app_req.await {
                 Ok(apps) => apps,
                 Err(e) => {
@@ -42,35 +201,252 @@ impl QBusiness {
                 }
             };
 
-            let apps = list_app.applications.unwrap_or(Vec::new());
+            let apps = list_app.applications.unwrap_or_default();
             let names = apps.into_iter().filter_map(|app| app.application_id).collect::<Vec<_>>();
-            Ok(names)
+            Python::with_gil(|py| Ok(names.into_py(py)))
         })
     }
 
-    fn chat<'p>(&self, py: Python<'p>, query: String, conversation: Option<String>, parent_msg: Option<String>) -> PyResult<&'p PyAny> {
-        let chat_req = chat_async(&self.client, &self.app_id, query, conversation, parent_msg);
+    #[pyo3(signature = (query, conversation=None, parent_msg=None, attachments=None, chat_mode=None))]
+    fn chat(
+        &self,
+        query: String,
+        conversation: Option<String>,
+        parent_msg: Option<String>,
+        attachments: Option<Vec<(String, Vec<u8>)>>,
+        chat_mode: Option<String>,
+    ) -> PyResult<Promise> {
+        let chat_req = chat_async(&self.client, &self.app_id, query, conversation, parent_msg, attachments, chat_mode);
 
-        pyo3_asyncio::tokio::future_into_py(py, async move {
+        Promise::spawn(async move {
             let chat_response = chat_req.await
                 .map_err(|e| PyException::new_err(format!("{:?}", e.into_service_error())))?;
             let stream = chat_response.output_stream;
             let q_streamer = QChatStream::new(stream);
-            Ok(q_streamer)
+            Python::with_gil(|py| Py::new(py, q_streamer).map(|obj| obj.into_py(py)))
         })
     }
+
+    /// Starts an OpenAI-compatible SSE server backed by this client: a `POST
+    /// /v1/chat/completions` relays the Q Business answer as `text/event-stream` chunks.
+    /// Runs on the shared runtime; call `ServerHandle.stop()` to shut it down.
+    fn serve(&self, host: String, port: u16) -> PyResult<ServerHandle> {
+        let state = ServeState { client: self.client.clone(), app_id: self.app_id.clone() };
+        let app = Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .with_state(state);
+
+        let addr: SocketAddr = format!("{host}:{port}")
+            .parse()
+            .map_err(|e| PyException::new_err(format!("invalid host/port: {e}")))?;
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+        runtime()?.spawn(async move {
+            let listener = match tokio::net::TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::error!(%addr, error = %e, "stream_q serve failed to bind");
+                    return;
+                }
+            };
+
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    // A dropped `ServerHandle` closes the channel without sending; only an
+                    // explicit `stop()` ends the server.
+                    if shutdown_rx.await.is_err() {
+                        std::future::pending::<()>().await;
+                    }
+                })
+                .await;
+        });
+
+        Ok(ServerHandle { shutdown: Some(shutdown_tx) })
+    }
+}
+
+/// Handle for the SSE server started by `QBusiness.serve`. Dropping it leaves the server
+/// running; call `stop()` to shut it down gracefully.
+#[pyclass]
+struct ServerHandle {
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+#[pymethods]
+impl ServerHandle {
+    fn stop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ServeState {
+    client: aws_sdk_qbusiness::Client,
+    app_id: String,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsRequest {
+    messages: Vec<ChatCompletionsMessage>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    parent_message_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsMessage {
+    role: String,
+    content: String,
+}
+
+/// A single `text/event-stream` frame in the shape OpenAI's `/v1/chat/completions` streaming
+/// clients expect (`object: "chat.completion.chunk"`, `choices[0].delta`, `finish_reason`).
+/// `citations` and `error` are Q Business-specific extensions on the delta; real OpenAI
+/// clients ignore fields they don't recognize.
+#[derive(Serialize)]
+struct ChatCompletionChunk {
+    id: String,
+    object: &'static str,
+    choices: [ChatCompletionChunkChoice; 1],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qbusiness_conversation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qbusiness_message_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChatCompletionChunkChoice {
+    index: u32,
+    delta: ChatCompletionDelta,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finish_reason: Option<&'static str>,
+}
+
+#[derive(Serialize, Default)]
+struct ChatCompletionDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citations: Option<Vec<Citation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Maps one `Output` (see `map_chat_event`) into a `chat.completion.chunk` frame. `id` is the
+/// stable completion id assigned once per request in `chat_completions`, the same on every
+/// chunk; the Q Business conversation/message ids ride along as separate fields instead of
+/// overloading `id` (they're only populated on the `metadata`-kind `Output`, at the end of the
+/// stream).
+fn to_chunk(id: &str, output: Output) -> ChatCompletionChunk {
+    let qbusiness_conversation_id = output.chat_id.clone();
+    let qbusiness_message_id = output.sys_msg_id.clone();
+
+    let (delta, finish_reason) = match output.kind.as_str() {
+        "text" => (
+            ChatCompletionDelta {
+                content: output.text,
+                citations: (!output.citations.is_empty()).then_some(output.citations),
+                error: None,
+            },
+            None,
+        ),
+        "metadata" => (ChatCompletionDelta::default(), Some("stop")),
+        "failed" => (
+            ChatCompletionDelta { error: output.error_message, ..Default::default() },
+            Some("content_filter"),
+        ),
+        _ => (ChatCompletionDelta { error: output.payload, ..Default::default() }, None),
+    };
+
+    ChatCompletionChunk {
+        id: id.to_string(),
+        object: "chat.completion.chunk",
+        choices: [ChatCompletionChunkChoice { index: 0, delta, finish_reason }],
+        qbusiness_conversation_id,
+        qbusiness_message_id,
+    }
+}
+
+/// Handles `POST /v1/chat/completions`: runs the chat on a background task and relays each
+/// mapped `Output` (see `map_chat_event`) as a `chat.completion.chunk` SSE `data:` frame,
+/// terminated by `[DONE]`.
+async fn chat_completions(
+    State(state): State<ServeState>,
+    Json(req): Json<ChatCompletionsRequest>,
+) -> Sse<impl futures_util::Stream<Item = Result<SseEvent, std::convert::Infallible>>> {
+    let query = req.messages.into_iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content)
+        .unwrap_or_default();
+
+    let id = format!("chatcmpl-{}", Uuid::new_v4());
+    let (tx, rx) = mpsc::channel(32);
+
+    tokio::spawn(async move {
+        let chat_req = chat_async(&state.client, &state.app_id, query, req.conversation_id, req.parent_message_id, None, None);
+
+        let chat_response = match chat_req.await {
+            Ok(response) => response,
+            Err(e) => {
+                let chunk = to_chunk(&id, Output::failed(format!("{:?}", e.into_service_error()), None));
+                let _ = tx.send(serde_json::to_string(&chunk).unwrap_or_default()).await;
+                let _ = tx.send("[DONE]".to_string()).await;
+                return;
+            }
+        };
+
+        let mut stream = chat_response.output_stream;
+        loop {
+            let event = match stream.recv().await {
+                Ok(Some(event)) => event,
+                Ok(None) | Err(_) => break,
+            };
+
+            let output = map_chat_event(event);
+
+            let frame = serde_json::to_string(&to_chunk(&id, output)).unwrap_or_default();
+            if tx.send(frame).await.is_err() {
+                break;
+            }
+        }
+
+        let _ = tx.send("[DONE]".to_string()).await;
+    });
+
+    Sse::new(ReceiverStream::new(rx).map(|data| Ok(SseEvent::default().data(data))))
 }
 
 fn chat_async(
     client: &aws_sdk_qbusiness::Client,
     app_id: &str,
-    query: String, conversation: Option<String>, parent_msg: Option<String>
+    query: String,
+    conversation: Option<String>,
+    parent_msg: Option<String>,
+    attachments: Option<Vec<(String, Vec<u8>)>>,
+    chat_mode: Option<String>,
 ) -> impl Future<Output = Result<ChatOutput, SdkError<ChatError>>> {
-    let inputs: Vec<Result<_, ChatInputStreamError>> = vec![
+    let mut inputs: Vec<Result<_, ChatInputStreamError>> = vec![
         Ok(ChatInputStream::TextEvent(TextInputEvent::builder().user_message(query).build().unwrap())),
-        Ok(ChatInputStream::EndOfInputEvent(EndOfInputEvent::builder().build()))
     ];
 
+    for (filename, bytes) in attachments.unwrap_or_default() {
+        let attachment = AttachmentInput::builder()
+            .name(filename)
+            .data(Blob::new(bytes))
+            .build()
+            .unwrap();
+        inputs.push(Ok(ChatInputStream::AttachmentEvent(
+            AttachmentInputEvent::builder().attachment(attachment).build()
+        )));
+    }
+
+    inputs.push(Ok(ChatInputStream::EndOfInputEvent(EndOfInputEvent::builder().build())));
+
     let inputs = futures_util::stream::iter(inputs);
     let input = inputs.into();
 
@@ -78,43 +454,95 @@ fn chat_async(
         .application_id(app_id)
         .set_conversation_id(conversation)
         .set_parent_message_id(parent_msg)
+        .set_chat_mode(chat_mode.map(ChatMode::from))
         .input_stream(input)
         .send()
 }
 
 type ChatEventReceiver = EventReceiver<ChatOutputStream, ChatOutputStreamError>;
 
+/// Pulls events from an in-flight chat response. This is *not* a Python async iterator:
+/// `Promise` has no `__await__`, so a `__anext__` returning one can't satisfy `async for`
+/// (`await`-ing a non-awaitable raises `TypeError`). Instead, call `next_event()` and either
+/// block on the returned `Promise` with `.wait()` or poll `.is_done()`, same as every other
+/// call in this module. `.wait()` raises `StopAsyncIteration` once the stream is exhausted
+/// or stopped, so callers can still drive a loop with a `try`/`except StopAsyncIteration`.
+/// A real `async fn __anext__` via pyo3's `experimental-async` feature would let this be a
+/// genuine coroutine again, but that requires pinning a pyo3 version bump in a `Cargo.toml`
+/// this repo doesn't have, so the polling contract above is what's actually shipped.
 #[pyclass]
 struct QChatStream {
     inner: Arc<Mutex<ChatEventReceiver>>,
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
 }
 
 impl QChatStream {
     fn new(stream_receiver: ChatEventReceiver) -> Self {
-        Self { inner: Arc::new(Mutex::new(stream_receiver)) }
+        Self {
+            inner: Arc::new(Mutex::new(stream_receiver)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            notify: Arc::new(Notify::new()),
+        }
     }
 }
 
+/// A source attribution annotating a span of a `TextEvent`'s text, as Q Business returns
+/// it alongside the generated answer.
+#[pyclass]
+#[derive(Clone, Serialize)]
+struct Citation {
+    #[pyo3(get)]
+    title: Option<String>,
+    #[pyo3(get)]
+    snippet: Option<String>,
+    #[pyo3(get)]
+    url: Option<String>,
+    #[pyo3(get)]
+    begin_offset: Option<i32>,
+    #[pyo3(get)]
+    end_offset: Option<i32>,
+}
+
 #[pyclass]
 struct Output {
+    #[pyo3(get)]
+    kind: String,
     #[pyo3(get)]
     text: Option<String>,
+    citations: Vec<Citation>,
     chat_id: Option<String>,
     user_msg_id: Option<String>,
     sys_msg_id: Option<String>,
+    #[pyo3(get)]
+    error_message: Option<String>,
+    #[pyo3(get)]
+    error_code: Option<String>,
+    #[pyo3(get)]
+    payload: Option<String>,
 }
 
 #[pymethods]
 impl Output {
+    #[getter]
+    fn citations(&self, py: Python<'_>) -> PyResult<Vec<Py<Citation>>> {
+        self.citations.iter().cloned().map(|c| Py::new(py, c)).collect()
+    }
+
     fn __repr__(&self) -> String {
-        if self.text.is_some() {
-            format!("Text({})", &self.text.as_ref().unwrap())
-        } else {
-            let empty = "".to_string();
-            let chat_id = self.chat_id.as_ref().unwrap_or(&empty);
-            let sys = self.sys_msg_id.as_ref().unwrap_or(&empty);
-            let usr = self.user_msg_id.as_ref().unwrap_or(&empty);
-            format!("Metadata{{ChatId: {}, Sys: {}, Usr: {}}}", chat_id, sys, usr)
+        match self.kind.as_str() {
+            "text" => format!("Text({})", self.text.as_deref().unwrap_or("")),
+            "metadata" => {
+                let empty = "".to_string();
+                let chat_id = self.chat_id.as_ref().unwrap_or(&empty);
+                let sys = self.sys_msg_id.as_ref().unwrap_or(&empty);
+                let usr = self.user_msg_id.as_ref().unwrap_or(&empty);
+                format!("Metadata{{ChatId: {}, Sys: {}, Usr: {}}}", chat_id, sys, usr)
+            }
+            "failed" => format!("Failed({:?}: {})", self.error_code, self.error_message.as_deref().unwrap_or("")),
+            "auth_challenge" => format!("AuthChallenge({})", self.payload.as_deref().unwrap_or("")),
+            "action_review" => format!("ActionReview({})", self.payload.as_deref().unwrap_or("")),
+            other => other.to_string(),
         }
     }
 
@@ -124,26 +552,186 @@ impl Output {
 }
 
 impl Output {
-    fn text(value: String) -> Self {
-        Self { text: Some(value), chat_id: None, sys_msg_id: None, user_msg_id: None }
+    fn text(value: String, citations: Vec<Citation>) -> Self {
+        Self {
+            kind: "text".to_string(),
+            text: Some(value),
+            citations,
+            chat_id: None,
+            user_msg_id: None,
+            sys_msg_id: None,
+            error_message: None,
+            error_code: None,
+            payload: None,
+        }
     }
 
     fn metadata(chat_id: String, user_msg: String, sys_msg: String) -> Self {
-        Self { text: None, chat_id: Some(chat_id), user_msg_id: Some(user_msg), sys_msg_id: Some(sys_msg) }
+        Self {
+            kind: "metadata".to_string(),
+            text: None,
+            citations: Vec::new(),
+            chat_id: Some(chat_id),
+            user_msg_id: Some(user_msg),
+            sys_msg_id: Some(sys_msg),
+            error_message: None,
+            error_code: None,
+            payload: None,
+        }
+    }
+
+    fn failed(error_message: String, error_code: Option<String>) -> Self {
+        Self {
+            kind: "failed".to_string(),
+            text: None,
+            citations: Vec::new(),
+            chat_id: None,
+            user_msg_id: None,
+            sys_msg_id: None,
+            error_message: Some(error_message),
+            error_code,
+            payload: None,
+        }
+    }
+
+    fn auth_challenge(payload: String) -> Self {
+        Self {
+            kind: "auth_challenge".to_string(),
+            text: None,
+            citations: Vec::new(),
+            chat_id: None,
+            user_msg_id: None,
+            sys_msg_id: None,
+            error_message: None,
+            error_code: None,
+            payload: Some(payload),
+        }
+    }
+
+    fn action_review(payload: String) -> Self {
+        Self {
+            kind: "action_review".to_string(),
+            text: None,
+            citations: Vec::new(),
+            chat_id: None,
+            user_msg_id: None,
+            sys_msg_id: None,
+            error_message: None,
+            error_code: None,
+            payload: Some(payload),
+        }
+    }
+
+    fn unknown(payload: String) -> Self {
+        Self {
+            kind: "unknown".to_string(),
+            text: None,
+            citations: Vec::new(),
+            chat_id: None,
+            user_msg_id: None,
+            sys_msg_id: None,
+            error_message: None,
+            error_code: None,
+            payload: Some(payload),
+        }
     }
 }
 
-#[pymethods]
-impl QChatStream {
-    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
-        slf
+/// Maps one `ChatOutputStream` variant to an `Output`. Shared by `QChatStream::next_event`
+/// and the SSE relay in `serve`, so citations and the other variant fields stay in sync
+/// between the two entry points.
+fn map_chat_event(event: ChatOutputStream) -> Output {
+    match event {
+        ChatOutputStream::TextEvent(text) => {
+            let citations = text.source_attribution.unwrap_or_default()
+                .into_iter()
+                .map(|attribution| {
+                    let (begin_offset, end_offset) = attribution.text_message_segments
+                        .unwrap_or_default()
+                        .into_iter()
+                        .next()
+                        .map(|segment| (Some(segment.begin_offset), Some(segment.end_offset)))
+                        .unwrap_or((None, None));
+
+                    Citation {
+                        title: attribution.title,
+                        snippet: attribution.snippet,
+                        url: attribution.url,
+                        begin_offset,
+                        end_offset,
+                    }
+                })
+                .collect();
+
+            Output::text(text.system_message.unwrap_or_default(), citations)
+        }
+        ChatOutputStream::MetadataEvent(metadata) => {
+            match (metadata.conversation_id, metadata.user_message_id, metadata.system_message_id) {
+                (Some(chat_id), Some(user_msg_id), Some(sys_msg_id)) => {
+                    Output::metadata(chat_id, user_msg_id, sys_msg_id)
+                }
+                // This now runs behind `chat_completions`, an unauthenticated network listener;
+                // a malformed `MetadataEvent` must surface as a `failed` `Output`, not panic the
+                // task and truncate the SSE stream with no error frame.
+                _ => Output::failed("MetadataEvent was missing a required id field".to_string(), None),
+            }
+        }
+        ChatOutputStream::FailedAttachmentEvent(failed) => {
+            let error = failed.attachment.and_then(|a| a.error);
+            let message = error.as_ref().and_then(|e| e.error_message.clone()).unwrap_or_default();
+            let code = error.and_then(|e| e.error_code.map(|c| format!("{:?}", c)));
+            Output::failed(message, code)
+        }
+        ChatOutputStream::AuthChallengeRequestEvent(challenge) => {
+            Output::auth_challenge(format!("{:?}", challenge))
+        }
+        ChatOutputStream::ActionReviewEvent(review) => {
+            Output::action_review(format!("{:?}", review))
+        }
+        // `ChatOutputStream` is `#[non_exhaustive]`; any variant we don't yet recognize still
+        // surfaces as a distinct `kind` carrying its Debug dump, same as `auth_challenge` and
+        // `action_review`, instead of silently vanishing into an empty text chunk.
+        other => Output::unknown(format!("{:?}", other)),
+    }
+}
+
+/// Waits for `fut` to resolve, unless `cancelled` is (or becomes) true first. `notify` must be
+/// the same `Notify` that `cancelled`'s setter calls `notify_waiters()` on.
+///
+/// The `Notified` future is created and `enable()`d *before* the `cancelled` check, per
+/// tokio's documented pattern for this exact race: `notify_waiters()` only wakes futures that
+/// are already registered as waiters, so checking the flag first and registering second would
+/// let a `stop()` landing in between go unobserved, leaving `fut` to await forever.
+async fn select_cancellable<F: Future>(cancelled: &AtomicBool, notify: &Notify, fut: F) -> Result<F::Output, ()> {
+    let notified = notify.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+
+    if cancelled.load(Ordering::SeqCst) {
+        return Err(());
+    }
+
+    tokio::select! {
+        biased;
+        _ = notified => Err(()),
+        value = fut => Ok(value),
     }
+}
 
-    fn __anext__<'a>(&self, py: Python<'a>) -> PyResult<Option<PyObject>> {
+#[pymethods]
+impl QChatStream {
+    /// Spawns a task that waits for the next event and resolves the returned `Promise` with
+    /// a `StopAsyncIteration` error once the stream is exhausted or `stop()`/`close()` is
+    /// called; see the type-level doc comment for the iteration contract.
+    fn next_event(&self) -> PyResult<Promise> {
         let receiver = self.inner.clone();
+        let cancelled = self.cancelled.clone();
+        let notify = self.notify.clone();
+
+        Promise::spawn(async move {
+            let next_event = select_cancellable(&cancelled, &notify, async { receiver.lock().await.recv().await }).await
+                .map_err(|()| PyStopAsyncIteration::new_err("stream was stopped"))?;
 
-        let future = pyo3_asyncio::tokio::future_into_py(py, async move {
-            let next_event = receiver.lock().await.recv().await;
             let next_event = match next_event {
                 Ok(n) => n,
                 Err(e) => {
@@ -156,28 +744,117 @@ impl QChatStream {
               return Err(PyStopAsyncIteration::new_err("Iterator exhausted"))
             };
 
-            let res = match next_event {
-                ChatOutputStream::TextEvent(text) => Output::text(text.system_message.unwrap_or("".to_string())),
-                ChatOutputStream::MetadataEvent(metadata) => Output::metadata(
-                    metadata.conversation_id.unwrap(),
-                    metadata.user_message_id.unwrap(),
-                    metadata.system_message_id.unwrap()
-                ),
-                _ => Output::text("".to_string())
-            };
+            let res = map_chat_event(next_event);
+            Python::with_gil(|py| Py::new(py, res).map(|obj| obj.into_py(py)))
+        })
+    }
 
-            Ok(Some(res))
-        });
+    /// Cancels the stream: a `next_event()` promise currently awaiting the next event
+    /// unblocks immediately, and this and every subsequent call raises `StopAsyncIteration`
+    /// instead of waiting on the underlying receiver.
+    fn stop(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
 
-        let result = future?;
-        Ok(Some(result.into()))
+    fn close(&self) {
+        self.stop()
     }
 }
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn stream_q(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(init, m)?)?;
     m.add_function(wrap_pyfunction!(new_q_client, m)?)?;
+    m.add_class::<Driver>()?;
+    m.add_class::<Promise>()?;
     m.add_class::<QChatStream>()?;
+    m.add_class::<Citation>()?;
+    m.add_class::<ServerHandle>()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn select_cancellable_observes_a_racing_cancel() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let notify = Arc::new(Notify::new());
+
+        let task_cancelled = cancelled.clone();
+        let task_notify = notify.clone();
+        let handle = tokio::spawn(async move {
+            select_cancellable(&task_cancelled, &task_notify, async {
+                // Stands in for a `recv()` that would otherwise never resolve on its own.
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "event"
+            })
+            .await
+        });
+
+        // Give the spawned task a chance to reach `select_cancellable`, register its
+        // `Notified` waiter and park in `select!` before we cancel, so the cancel has to
+        // wake an already-waiting task.
+        tokio::task::yield_now().await;
+
+        cancelled.store(true, Ordering::SeqCst);
+        notify.notify_waiters();
+
+        let result = tokio::time::timeout(Duration::from_millis(500), handle)
+            .await
+            .expect("cancellation should unblock the wait promptly")
+            .expect("task should not panic");
+
+        assert_eq!(result, Err(()));
+    }
+
+    #[tokio::test]
+    async fn select_cancellable_resolves_normally_when_not_cancelled() {
+        let cancelled = AtomicBool::new(false);
+        let notify = Notify::new();
+
+        let result = select_cancellable(&cancelled, &notify, async { "event" }).await;
+
+        assert_eq!(result, Ok("event"));
+    }
+
+    #[test]
+    fn to_chunk_maps_text_output_to_a_chat_completion_chunk() {
+        let chunk = to_chunk("msg-1", Output::text("hello".to_string(), vec![]));
+        let json = serde_json::to_value(&chunk).unwrap();
+
+        assert_eq!(json["id"], "msg-1");
+        assert_eq!(json["object"], "chat.completion.chunk");
+        assert_eq!(json["choices"][0]["delta"]["content"], "hello");
+        assert!(json["choices"][0]["finish_reason"].is_null());
+        // The internal `Output::kind` discriminant must not leak onto the wire.
+        assert!(json.get("kind").is_none());
+    }
+
+    #[test]
+    fn to_chunk_maps_metadata_output_to_a_stop_finish_reason() {
+        let chunk = to_chunk("chatcmpl-1", Output::metadata("chat-1".to_string(), "user-1".to_string(), "sys-1".to_string()));
+        let json = serde_json::to_value(&chunk).unwrap();
+
+        // The completion id stays the stable one passed in; Q Business's own ids ride
+        // along as separate fields instead of overwriting it.
+        assert_eq!(json["id"], "chatcmpl-1");
+        assert_eq!(json["qbusiness_conversation_id"], "chat-1");
+        assert_eq!(json["qbusiness_message_id"], "sys-1");
+        assert_eq!(json["choices"][0]["finish_reason"], "stop");
+        assert!(json["choices"][0]["delta"].as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn to_chunk_maps_failed_output_to_a_content_filter_finish_reason() {
+        let chunk = to_chunk("", Output::failed("boom".to_string(), None));
+        let json = serde_json::to_value(&chunk).unwrap();
+
+        assert_eq!(json["choices"][0]["finish_reason"], "content_filter");
+        assert_eq!(json["choices"][0]["delta"]["error"], "boom");
+    }
+}